@@ -0,0 +1,372 @@
+//! Pluggable volume sources for [`VolumeBlock`](super::VolumeBlock).
+//!
+//! `amixer` isn't available on PipeWire/PulseAudio-only systems that don't ship ALSA's
+//! userspace tools, so the actual "go read the mixer" logic is extracted behind the
+//! [`VolumeBackend`] trait. Each implementation knows how to invoke and parse exactly one CLI
+//! tool; [`resolve`] picks the first one found on `PATH`, unless a [`Kind`] is pinned explicitly.
+
+use crate::errors::UpdateError;
+use std::process::{Command, Stdio};
+
+/// A source of volume/mute readings and control, backed by some external mixer CLI.
+pub trait VolumeBackend {
+    /// Reads the current volume as a 0-100 percent and whether it's muted.
+    fn read(&self) -> Result<(i32, bool), UpdateError>;
+
+    /// Raises (positive) or lowers (negative) the volume by `delta_percent`.
+    fn adjust_volume(&self, delta_percent: i32) -> Result<(), UpdateError>;
+
+    /// Toggles mute.
+    fn toggle_mute(&self) -> Result<(), UpdateError>;
+
+    /// The name of the backend, for use in error messages and config.
+    fn name(&self) -> &'static str;
+}
+
+/// Explicit backend selection, bypassing [`resolve`]'s auto-detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Amixer,
+    Pamixer,
+    Pactl,
+    Wpctl,
+}
+
+/// Builds the requested backend, or auto-detects one by checking `PATH` for `amixer`,
+/// `pamixer`, `pactl`, and `wpctl`, in that order. `device`/`control` only apply to the
+/// `amixer` backend. Returns `None` if `kind` is `None` and nothing was found.
+pub fn resolve(
+    kind: Option<Kind>,
+    device: Option<String>,
+    control: String,
+) -> Option<Box<dyn VolumeBackend>> {
+    let kind = kind.or_else(|| {
+        [Kind::Amixer, Kind::Pamixer, Kind::Pactl, Kind::Wpctl]
+            .into_iter()
+            .find(|kind| binary_exists(kind.binary_name()))
+    })?;
+
+    Some(match kind {
+        Kind::Amixer => Box::new(AmixerBackend { device, control }),
+        Kind::Pamixer => Box::new(PamixerBackend),
+        Kind::Pactl => Box::new(PactlBackend),
+        Kind::Wpctl => Box::new(WpctlBackend),
+    })
+}
+
+impl Kind {
+    fn binary_name(self) -> &'static str {
+        match self {
+            Kind::Amixer => "amixer",
+            Kind::Pamixer => "pamixer",
+            Kind::Pactl => "pactl",
+            Kind::Wpctl => "wpctl",
+        }
+    }
+}
+
+fn binary_exists(bin: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(bin).is_file()))
+        .unwrap_or(false)
+}
+
+fn parse_error(bin: &str, output: &str) -> UpdateError {
+    UpdateError {
+        block_name: String::from("volume"),
+        message: format!("couldn't parse `{}` output: `{}`", bin, output),
+    }
+}
+
+fn run_ok(bin: &str, args: &[&str]) -> Result<(), UpdateError> {
+    // these commands print a status line to stdout on success; that would otherwise land on
+    // the bar's own stdout and corrupt its protocol, so it's thrown away rather than inherited
+    let status = Command::new(bin)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| UpdateError {
+            block_name: String::from("volume"),
+            message: format!("couldn't run `{}`: {}", bin, e),
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(UpdateError {
+            block_name: String::from("volume"),
+            message: format!("`{} {}` exited with {}", bin, args.join(" "), status),
+        })
+    }
+}
+
+fn run_trimmed(bin: &str, args: &[&str]) -> Result<String, UpdateError> {
+    let output = Command::new(bin).args(args).output().map_err(|e| UpdateError {
+        block_name: String::from("volume"),
+        message: format!("couldn't run `{}`: {}", bin, e),
+    })?;
+
+    String::from_utf8(output.stdout)
+        .map(|s| s.trim().to_string())
+        .map_err(|e| UpdateError {
+            block_name: String::from("volume"),
+            message: format!("`{}` produced non-utf8 output: {}", bin, e),
+        })
+}
+
+/// Parses `amixer sget`'s stdout, e.g. ` Mono: Playback 50 [65%] [-10.50dB] [on]`.
+fn parse_amixer(stdout: &str) -> Result<(i32, bool), UpdateError> {
+    let last_line = stdout
+        .lines()
+        .last()
+        .ok_or_else(|| parse_error("amixer", stdout))?;
+
+    // take the percent out of the *first* bracket specifically, since later brackets (dB,
+    // on/off) also contain digits and would otherwise get mixed in.
+    let first_bracket = last_line
+        .find('[')
+        .map(|i| &last_line[i..])
+        .ok_or_else(|| parse_error("amixer", last_line))?;
+
+    let muted = if first_bracket.contains("[off]") {
+        true
+    } else if first_bracket.contains("[on]") {
+        false
+    } else {
+        return Err(UpdateError {
+            block_name: String::from("volume"),
+            message: String::from("couldn't tell from amixer output if the control is muted"),
+        });
+    };
+
+    let percent_field = first_bracket
+        .find(']')
+        .map(|i| &first_bracket[..i])
+        .ok_or_else(|| parse_error("amixer", last_line))?;
+
+    let raw_percent: String = percent_field.chars().filter(|c| c.is_ascii_digit()).collect();
+    let percent = raw_percent.parse::<i32>().map_err(|e| UpdateError {
+        block_name: String::from("volume"),
+        message: format!("couldn't parse volume from `{}`: {}", raw_percent, e),
+    })?;
+
+    Ok((percent, muted))
+}
+
+/// Parses the stdout of `pamixer --get-volume` (a bare integer) and `--get-mute` (`true`/`false`).
+fn parse_pamixer(volume_stdout: &str, mute_stdout: &str) -> Result<(i32, bool), UpdateError> {
+    let percent = volume_stdout
+        .parse::<i32>()
+        .map_err(|_| parse_error("pamixer --get-volume", volume_stdout))?;
+
+    Ok((percent, mute_stdout == "true"))
+}
+
+/// Parses `pactl get-sink-volume`'s stdout (e.g. `Volume: front-left: 65536 / 65% / ...`) and
+/// `get-sink-mute`'s stdout (e.g. `Mute: yes`).
+fn parse_pactl(volume_stdout: &str, mute_stdout: &str) -> Result<(i32, bool), UpdateError> {
+    let percent = volume_stdout
+        .split_whitespace()
+        .find_map(|field| field.strip_suffix('%'))
+        .and_then(|p| p.parse::<i32>().ok())
+        .ok_or_else(|| parse_error("pactl get-sink-volume", volume_stdout))?;
+
+    let muted = mute_stdout.trim_end().ends_with("yes");
+
+    Ok((percent, muted))
+}
+
+/// Parses `wpctl get-volume`'s stdout, e.g. `Volume: 0.65` or `Volume: 0.00 [MUTED]`.
+fn parse_wpctl(stdout: &str) -> Result<(i32, bool), UpdateError> {
+    let muted = stdout.contains("[MUTED]");
+
+    let fraction = stdout
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| v.parse::<f64>().ok())
+        .ok_or_else(|| parse_error("wpctl get-volume", stdout))?;
+
+    Ok(((fraction * 100.0).round() as i32, muted))
+}
+
+/// ALSA's `amixer`, e.g. `amixer sget Master`.
+pub struct AmixerBackend {
+    pub device: Option<String>,
+    pub control: String,
+}
+
+impl VolumeBackend for AmixerBackend {
+    fn name(&self) -> &'static str {
+        "amixer"
+    }
+
+    fn read(&self) -> Result<(i32, bool), UpdateError> {
+        let mut args = Vec::new();
+        if let Some(device) = &self.device {
+            args.push("-D");
+            args.push(device.as_str());
+        }
+        args.push("sget");
+        args.push(&self.control);
+
+        let stdout = run_trimmed("amixer", &args)?;
+        parse_amixer(&stdout)
+    }
+
+    fn adjust_volume(&self, delta_percent: i32) -> Result<(), UpdateError> {
+        let sign = if delta_percent >= 0 { "+" } else { "-" };
+        let step = format!("{}%{}", delta_percent.abs(), sign);
+
+        let mut args = Vec::new();
+        if let Some(device) = &self.device {
+            args.push("-D");
+            args.push(device.as_str());
+        }
+        args.push("sset");
+        args.push(&self.control);
+        args.push(&step);
+
+        run_ok("amixer", &args)
+    }
+
+    fn toggle_mute(&self) -> Result<(), UpdateError> {
+        let mut args = Vec::new();
+        if let Some(device) = &self.device {
+            args.push("-D");
+            args.push(device.as_str());
+        }
+        args.push("sset");
+        args.push(&self.control);
+        args.push("toggle");
+
+        run_ok("amixer", &args)
+    }
+}
+
+/// `pamixer`, PipeWire/PulseAudio's CLI mixer (`pamixer --get-volume` / `--get-mute`).
+pub struct PamixerBackend;
+
+impl VolumeBackend for PamixerBackend {
+    fn name(&self) -> &'static str {
+        "pamixer"
+    }
+
+    fn read(&self) -> Result<(i32, bool), UpdateError> {
+        let volume = run_trimmed("pamixer", &["--get-volume"])?;
+        let mute = run_trimmed("pamixer", &["--get-mute"])?;
+        parse_pamixer(&volume, &mute)
+    }
+
+    fn adjust_volume(&self, delta_percent: i32) -> Result<(), UpdateError> {
+        let flag = if delta_percent >= 0 { "-i" } else { "-d" };
+        let amount = delta_percent.abs().to_string();
+        run_ok("pamixer", &[flag, &amount])
+    }
+
+    fn toggle_mute(&self) -> Result<(), UpdateError> {
+        run_ok("pamixer", &["-t"])
+    }
+}
+
+/// `pactl`, PulseAudio's own CLI (`pactl get-sink-volume`/`get-sink-mute @DEFAULT_SINK@`).
+pub struct PactlBackend;
+
+impl VolumeBackend for PactlBackend {
+    fn name(&self) -> &'static str {
+        "pactl"
+    }
+
+    fn read(&self) -> Result<(i32, bool), UpdateError> {
+        let volume = run_trimmed("pactl", &["get-sink-volume", "@DEFAULT_SINK@"])?;
+        let mute = run_trimmed("pactl", &["get-sink-mute", "@DEFAULT_SINK@"])?;
+        parse_pactl(&volume, &mute)
+    }
+
+    fn adjust_volume(&self, delta_percent: i32) -> Result<(), UpdateError> {
+        let sign = if delta_percent >= 0 { "+" } else { "-" };
+        let step = format!("{}{}%", sign, delta_percent.abs());
+        run_ok("pactl", &["set-sink-volume", "@DEFAULT_SINK@", &step])
+    }
+
+    fn toggle_mute(&self) -> Result<(), UpdateError> {
+        run_ok("pactl", &["set-sink-mute", "@DEFAULT_SINK@", "toggle"])
+    }
+}
+
+/// `wpctl`, WirePlumber's CLI (`wpctl get-volume @DEFAULT_AUDIO_SINK@`).
+pub struct WpctlBackend;
+
+impl VolumeBackend for WpctlBackend {
+    fn name(&self) -> &'static str {
+        "wpctl"
+    }
+
+    fn read(&self) -> Result<(i32, bool), UpdateError> {
+        let output = run_trimmed("wpctl", &["get-volume", "@DEFAULT_AUDIO_SINK@"])?;
+        parse_wpctl(&output)
+    }
+
+    fn adjust_volume(&self, delta_percent: i32) -> Result<(), UpdateError> {
+        let sign = if delta_percent >= 0 { "+" } else { "-" };
+        let step = format!("{}%{}", delta_percent.abs(), sign);
+        run_ok("wpctl", &["set-volume", "@DEFAULT_AUDIO_SINK@", &step])
+    }
+
+    fn toggle_mute(&self) -> Result<(), UpdateError> {
+        run_ok("wpctl", &["set-mute", "@DEFAULT_AUDIO_SINK@", "toggle"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amixer_parses_simple_unmuted_output() {
+        let stdout = "Simple mixer control 'Master',0\n  Front Left: Playback 32768 [50%] [-10.50dB] [on]";
+        assert_eq!(parse_amixer(stdout).unwrap(), (50, false));
+    }
+
+    #[test]
+    fn amixer_multi_bracket_doesnt_bleed_db_digits_into_percent() {
+        // the dB bracket's digits (and the `.`/`-`) must not get merged into the percent just
+        // because both are bracketed -- this was the bug that motivated the VolumeBackend split
+        let stdout = " Mono: Playback 50 [65%] [-10.50dB] [on]";
+        assert_eq!(parse_amixer(stdout).unwrap(), (65, false));
+    }
+
+    #[test]
+    fn amixer_off_is_muted() {
+        let stdout = " Mono: Playback 0 [0%] [-inf dB] [off]";
+        assert_eq!(parse_amixer(stdout).unwrap(), (0, true));
+    }
+
+    #[test]
+    fn amixer_rejects_output_with_no_bracket() {
+        assert!(parse_amixer("Simple mixer control 'Master',0").is_err());
+    }
+
+    #[test]
+    fn pamixer_parses_volume_and_mute() {
+        assert_eq!(parse_pamixer("42", "false").unwrap(), (42, false));
+        assert_eq!(parse_pamixer("0", "true").unwrap(), (0, true));
+    }
+
+    #[test]
+    fn pactl_parses_percent_from_first_channel_and_mute() {
+        let volume = "Volume: front-left: 65536 / 100% / 0.00 dB,   front-right: 65536 / 100% / 0.00 dB";
+        assert_eq!(parse_pactl(volume, "Mute: no").unwrap(), (100, false));
+        assert_eq!(parse_pactl(volume, "Mute: yes").unwrap(), (100, true));
+    }
+
+    #[test]
+    fn wpctl_parses_fraction_as_percent() {
+        assert_eq!(parse_wpctl("Volume: 0.50\n").unwrap(), (50, false));
+    }
+
+    #[test]
+    fn wpctl_detects_muted_marker() {
+        assert_eq!(parse_wpctl("Volume: 0.34 [MUTED]\n").unwrap(), (34, true));
+    }
+}
@@ -2,94 +2,255 @@ use crate::errors::*;
 use crate::format::blocks::output::{BlockOutputContent, NiceOutput};
 use crate::format::blocks::Block;
 use crate::format::Attention;
-use std::process;
 
-/// VolumeBlock provides information for the system's audio volume. Requires `amixer`.
-#[derive(Default)]
+mod backend;
+#[cfg(feature = "pulseaudio")]
+mod pulse;
+
+use backend::VolumeBackend;
+use std::time::{Duration, Instant};
+
+/// VolumeBlock provides information for the system's audio volume. Auto-detects a backend on
+/// `PATH` (`amixer`, `pamixer`, `pactl`, or `wpctl`, in that order, unless pinned via
+/// [`with_backend`](Self::with_backend)); with the `pulseaudio` feature enabled, it instead
+/// prefers an event-driven connection to the PulseAudio/PipeWire daemon over polling any of them.
 pub struct VolumeBlock {
     current_volume: i32,
     muted: bool,
+    /// `amixer` device, e.g. `hw:1`. `None` uses amixer's default.
+    device: Option<String>,
+    /// `amixer` control/simple-mixer element, e.g. `Master` or `Headphone`.
+    control: String,
+    /// Template used to render `output()` while unmuted. Supports `{icon}` and `{perc}`.
+    format: String,
+    /// Template used to render `output()` while muted or at 0%. Supports `{icon}` and `{perc}`.
+    mute_format: String,
+    /// Percentage adjusted per scroll tick.
+    step: i32,
+    /// Explicit backend choice. `None` means auto-detect on first use.
+    backend_kind: Option<backend::Kind>,
+    /// The resolved backend, built lazily so builder methods can still change `device`,
+    /// `control`, and `backend_kind` beforehand.
+    backend: Option<Box<dyn VolumeBackend>>,
+    /// Set when no backend could be found on `PATH`, so we stop re-probing every cycle and
+    /// only retry on [`Self::BACKEND_RECHECK_SECONDS`].
+    backend_missing_since: Option<Instant>,
+    /// Set when a resolved backend's read fails, so we don't immediately re-invoke it on the
+    /// very next update but wait out [`Self::BACKEND_RECHECK_SECONDS`]. Cleared on the next
+    /// successful read.
+    read_failing_since: Option<Instant>,
+    /// True once reads have failed persistently (or no backend exists), driving the
+    /// "unavailable" output state.
+    unavailable: bool,
+    #[cfg(feature = "pulseaudio")]
+    pulse: Option<pulse::PulseHandle>,
+}
+
+impl Default for VolumeBlock {
+    fn default() -> Self {
+        Self {
+            current_volume: 0,
+            muted: false,
+            device: None,
+            control: String::from("Master"),
+            format: String::from("{perc}%"),
+            mute_format: String::from("Muted"),
+            step: 5,
+            backend_kind: None,
+            backend: None,
+            backend_missing_since: None,
+            read_failing_since: None,
+            unavailable: false,
+            #[cfg(feature = "pulseaudio")]
+            pulse: None,
+        }
+    }
 }
 
 impl VolumeBlock {
     /// Returns a new VolumeBlock. By default, it gets info for the Master bus via `amixer`.
+    ///
+    /// With the `pulseaudio` feature enabled, this first tries to connect to the PulseAudio
+    /// daemon for event-driven updates, falling back to polling the CLI backend if that fails.
     pub fn new() -> Self {
-        Default::default()
-    }
-
-    const MAX_WAIT_SECONDS: u64 = 30;
-
-    fn get_volume_info(&self) -> String {
-        let mut wait_time_seconds = 1;
-        loop {
-            if let Ok(output) = process::Command::new("amixer")
-                .args(&["sget", "Master"])
-                .output()
-            {
-                if let Ok(info) = String::from_utf8(output.stdout) {
-                    if let Some(last_line) = info.lines().last() {
-                        return last_line.to_string();
-                    }
-                }
-            }
+        #[allow(unused_mut)]
+        let mut block = Self::default();
 
-            std::thread::sleep(std::time::Duration::from_secs(wait_time_seconds));
+        #[cfg(feature = "pulseaudio")]
+        {
+            block.pulse = pulse::spawn();
+        }
 
-            // exponential falloff
-            if wait_time_seconds < Self::MAX_WAIT_SECONDS {
-                wait_time_seconds = Self::MAX_WAIT_SECONDS.min(wait_time_seconds * 2);
+        block
+    }
+
+    /// Queries a specific `amixer` device (e.g. `hw:1`) instead of the default one.
+    pub fn with_device(mut self, device: impl Into<String>) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Queries a control/simple-mixer element other than `Master`, e.g. `Headphone` or `Capture`.
+    pub fn with_control(mut self, control: impl Into<String>) -> Self {
+        self.control = control.into();
+        self
+    }
+
+    /// Sets the template used to render `output()` while unmuted. Supports the tokens `{icon}`
+    /// and `{perc}`.
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = format.into();
+        self
+    }
+
+    /// Sets the template used to render `output()` while muted or at 0%. Supports the tokens
+    /// `{icon}` and `{perc}`.
+    pub fn with_mute_format(mut self, mute_format: impl Into<String>) -> Self {
+        self.mute_format = mute_format.into();
+        self
+    }
+
+    /// Pins the block to a specific backend instead of auto-detecting one from `PATH`.
+    pub fn with_backend(mut self, kind: backend::Kind) -> Self {
+        self.backend_kind = Some(kind);
+        self
+    }
+
+    /// Sets how many percentage points each scroll tick raises or lowers the volume by.
+    pub fn with_step(mut self, percent: i32) -> Self {
+        self.step = percent;
+        self
+    }
+
+    const BACKEND_RECHECK_SECONDS: u64 = 60;
+
+    /// Returns the resolved backend, detecting (or constructing the pinned) one on first use.
+    ///
+    /// Once detection fails, it isn't retried on every cycle; we only probe `PATH` again after
+    /// [`Self::BACKEND_RECHECK_SECONDS`] have passed, so a machine with no mixer binary doesn't
+    /// spin a `PATH` scan on every update.
+    fn backend(&mut self) -> Option<&dyn VolumeBackend> {
+        if self.backend.is_some() {
+            return self.backend.as_deref();
+        }
+
+        if let Some(missing_since) = self.backend_missing_since {
+            if missing_since.elapsed() < Duration::from_secs(Self::BACKEND_RECHECK_SECONDS) {
+                return None;
             }
         }
+
+        self.backend = backend::resolve(self.backend_kind, self.device.clone(), self.control.clone());
+        self.backend_missing_since = if self.backend.is_none() {
+            Some(Instant::now())
+        } else {
+            None
+        };
+
+        self.backend.as_deref()
     }
 
-    // returns the current volume percentage as an i32, or zero
-    // if muted
     fn update_current_volume(&mut self) -> Result<(), UpdateError> {
-        let info = self.get_volume_info();
-
-        match info.chars().position(|c| c == '[') {
-            Some(i) => {
-                let line_end = &info[i..];
-
-                // first, are we muted?
-                self.muted = if line_end.contains("on") {
-                    false
-                } else if line_end.contains("off") {
-                    true
-                } else {
-                    return Err(UpdateError {
-                        block_name: String::from("volume"),
-                        message: String::from(
-                            "couldn't parse if volume is definitely muted or not",
-                        ),
-                    });
-                };
-
-                if !self.muted {
-                    // filters out any non-digit characters past the first opening bracket to parse the
-                    // volume amount
-                    let raw_percent = line_end
-                        .chars()
-                        .filter(|c| c.is_digit(10))
-                        .collect::<String>();
-
-                    self.current_volume = raw_percent.parse::<i32>().map_err(|e| UpdateError {
-                        block_name: String::from("volume"),
-                        message: format!("couldn't parse volume from `{}`: {}", raw_percent, e),
-                    })?;
-                }
+        #[cfg(feature = "pulseaudio")]
+        if let Some(pulse) = &self.pulse {
+            if let Some(reading) = pulse.try_recv() {
+                self.current_volume = reading.percent;
+                self.muted = reading.muted;
+                self.unavailable = false;
+            }
 
+            // pulse is connected; whether or not this tick had a fresh reading, don't fall
+            // through to the CLI backend below. Most ticks land between subscribe events, and
+            // polling a backend on top of an already-live pulse connection would both defeat the
+            // point of it and spuriously mark the block unavailable on CLI-less PipeWire boxes.
+            return Ok(());
+        }
+
+        if self.backend().is_none() {
+            self.unavailable = true;
+            return Err(UpdateError {
+                block_name: String::from("volume"),
+                message: String::from(
+                    "no volume backend found on PATH (tried amixer, pamixer, pactl, wpctl)",
+                ),
+            });
+        }
+
+        // a backend that's resolved but persistently failing to read (daemon down, permission
+        // error, renamed control, etc.) gets the same recheck-throttle as a missing binary, so
+        // we don't re-invoke it below on every single update() call
+        if let Some(failing_since) = self.read_failing_since {
+            if failing_since.elapsed() < Duration::from_secs(Self::BACKEND_RECHECK_SECONDS) {
+                self.unavailable = true;
+                return Err(UpdateError {
+                    block_name: String::from("volume"),
+                    message: String::from(
+                        "backend reads are still failing; not retrying until the recheck timer elapses",
+                    ),
+                });
+            }
+        }
+
+        // one fast attempt per update() call -- no in-process retry loop, so a slow or wedged
+        // backend can never block an update thread. A failure here just starts the
+        // read_failing_since throttle above, which spaces subsequent attempts out across
+        // BACKEND_RECHECK_SECONDS instead of busy-retrying in place.
+        let backend_name = self.backend().expect("checked above").name();
+
+        match self.backend().expect("checked above").read() {
+            Ok((percent, muted)) => {
+                self.current_volume = percent;
+                self.muted = muted;
+                self.unavailable = false;
+                self.read_failing_since = None;
                 Ok(())
             }
-            None => Err(UpdateError {
-                block_name: String::from("volume"),
-                message: String::from("couldn't parse amixer output"),
-            }),
+            Err(e) => {
+                self.unavailable = true;
+                self.read_failing_since = Some(Instant::now());
+                Err(UpdateError {
+                    block_name: String::from("volume"),
+                    message: format!("`{}` read failed: {}", backend_name, e.message),
+                })
+            }
         }
     }
 
+    /// Raises the volume by [`Self::with_step`]'s configured percent and immediately refreshes
+    /// `current_volume`/`muted` so the next `output()` reflects the change without waiting for
+    /// the next scheduled update.
+    ///
+    /// `crate::format::blocks::Block` doesn't yet have a click/scroll hook to wire this up to,
+    /// so for now it's a plain method the eventual hook can call once that trait grows one.
+    pub fn scroll_up(&mut self) {
+        self.adjust_volume(self.step);
+    }
+
+    /// Lowers the volume by [`Self::with_step`]'s configured percent. See [`Self::scroll_up`].
+    pub fn scroll_down(&mut self) {
+        let step = self.step;
+        self.adjust_volume(-step);
+    }
+
+    /// Toggles mute on the resolved backend. See [`Self::scroll_up`].
+    pub fn toggle_mute(&mut self) {
+        if let Some(backend) = self.backend() {
+            let _ = backend.toggle_mute();
+        }
+        let _ = self.update_current_volume();
+    }
+
+    fn adjust_volume(&mut self, delta_percent: i32) {
+        if let Some(backend) = self.backend() {
+            let _ = backend.adjust_volume(delta_percent);
+        }
+        let _ = self.update_current_volume();
+    }
+
     fn get_icon(&self) -> char {
-        if self.current_volume == 0 {
+        if self.unavailable {
+            UNAVAILABLE_ICON
+        } else if self.current_volume == 0 {
             ZERO_ICON
         } else if self.muted {
             MUTE_ICON
@@ -114,17 +275,41 @@ impl Block for VolumeBlock {
     }
 
     fn next_update_time(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        // when the pulse backend is active and has a fresh reading waiting, ask the scheduler
+        // to re-run update() immediately instead of waiting for the next poll
+        #[cfg(feature = "pulseaudio")]
+        if let Some(pulse) = &self.pulse {
+            if pulse.is_dirty() {
+                return Some(chrono::Local::now());
+            }
+        }
+
         None
     }
 
     fn output(&self) -> Option<BlockOutputContent> {
+        let icon = self.get_icon();
+
+        if self.unavailable {
+            return Some(BlockOutputContent::Nice(NiceOutput {
+                icon,
+                primary_text: String::from("N/A"),
+                secondary_text: None,
+                attention: Attention::Dim,
+            }));
+        }
+
+        let template = if self.muted || self.current_volume == 0 {
+            &self.mute_format
+        } else {
+            &self.format
+        };
+
         Some(BlockOutputContent::Nice(NiceOutput {
-            icon: self.get_icon(),
-            primary_text: if self.muted || self.current_volume == 0 {
-                String::from("Muted")
-            } else {
-                format!("{}%", self.current_volume)
-            },
+            icon,
+            primary_text: template
+                .replace("{icon}", &icon.to_string())
+                .replace("{perc}", &self.current_volume.to_string()),
             secondary_text: None,
             attention: Attention::Dim,
         }))
@@ -134,3 +319,4 @@ impl Block for VolumeBlock {
 const VOLUME_ICONS: [char; 3] = ['\u{F057F}', '\u{F0580}', '\u{F057E}'];
 const MUTE_ICON: char = '\u{F0581}';
 const ZERO_ICON: char = '\u{F0E08}';
+const UNAVAILABLE_ICON: char = '\u{F0A8A}';
@@ -0,0 +1,213 @@
+//! Native PulseAudio backend for [`VolumeBlock`](super::VolumeBlock), gated behind the
+//! `pulseaudio` feature.
+//!
+//! `amixer` has to be polled on a timer, so a volume change made by another app (or the
+//! hardware keys, via `pulseaudio`'s own key handling) isn't reflected until the next
+//! scheduled update. This module instead spawns a background thread that runs a libpulse
+//! `Mainloop`, subscribes to sink and server-change events, and keeps a dirty flag + the most
+//! recent reading around for [`VolumeBlock`](super::VolumeBlock) to pick up. The block surfaces
+//! the dirty flag through `next_update_time()` so the scheduler re-emits `output()` on the very
+//! next tick instead of waiting out the normal polling interval.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use libpulse_binding::context::subscribe::{Facility, InterestMaskSet};
+use libpulse_binding::context::{Context, FlagSet as ContextFlagSet, State as ContextState};
+use libpulse_binding::mainloop::threaded::Mainloop;
+use libpulse_binding::proplist::Proplist;
+use libpulse_binding::volume::Volume;
+
+/// A single volume reading pulled from the default sink.
+#[derive(Clone, Copy, Debug)]
+pub struct PulseReading {
+    pub percent: i32,
+    pub muted: bool,
+}
+
+/// Handle to the background PulseAudio subscription thread.
+pub struct PulseHandle {
+    rx: Receiver<PulseReading>,
+    dirty: Arc<AtomicBool>,
+}
+
+impl PulseHandle {
+    /// Returns `true` if a sink or server change has come in since the last `try_recv()`.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Relaxed)
+    }
+
+    /// Drains the channel and returns the most recent reading, if any, clearing the dirty flag.
+    pub fn try_recv(&self) -> Option<PulseReading> {
+        let mut latest = None;
+        while let Ok(reading) = self.rx.try_recv() {
+            latest = Some(reading);
+        }
+
+        self.dirty.store(false, Ordering::Relaxed);
+        latest
+    }
+}
+
+/// Spawns the subscription thread and connects to the default PulseAudio server.
+///
+/// Returns `None` if a mainloop/context couldn't be created or the daemon is unreachable, in
+/// which case the caller should fall back to polling a CLI backend.
+pub fn spawn() -> Option<PulseHandle> {
+    let (tx, rx) = mpsc::channel();
+    let dirty = Arc::new(AtomicBool::new(false));
+
+    let ready = Arc::new(AtomicBool::new(false));
+    let ready_for_thread = Arc::clone(&ready);
+    let dirty_for_thread = Arc::clone(&dirty);
+
+    thread::Builder::new()
+        .name("muse-status-pulse".into())
+        .spawn(move || run(tx, dirty_for_thread, ready_for_thread))
+        .ok()?;
+
+    // give the thread a moment to report whether it actually managed to connect, so `new()`
+    // can fall back to a CLI backend instead of silently sitting on a dead channel
+    for _ in 0..50 {
+        if ready.load(Ordering::Relaxed) {
+            return Some(PulseHandle { rx, dirty });
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    None
+}
+
+/// Sets up and starts the threaded mainloop, which then runs on its own internal thread while
+/// this one parks. The mainloop/context are only ever touched from
+/// here (during setup) and from the callbacks libpulse invokes while we hold its lock, so a
+/// plain `Rc<RefCell<_>>` is enough -- no `Arc`/`Mutex` needed.
+fn run(tx: Sender<PulseReading>, dirty: Arc<AtomicBool>, ready: Arc<AtomicBool>) {
+    let proplist = match Proplist::new() {
+        Some(p) => p,
+        None => return,
+    };
+
+    let mainloop = match Mainloop::new() {
+        Some(m) => Rc::new(RefCell::new(m)),
+        None => return,
+    };
+
+    let context = match Context::new_with_proplist(&*mainloop.borrow(), "muse-status", &proplist) {
+        Some(c) => Rc::new(RefCell::new(c)),
+        None => return,
+    };
+
+    // `wait()` below only returns once `signal()` is called, so the context needs to signal
+    // the mainloop on every state transition while we're waiting for it to come up -- this is
+    // the same state-callback/signal pairing shown in libpulse-binding's own threaded mainloop
+    // example. This thread holds a `borrow_mut()` on the mainloop for the whole of `wait()`, so
+    // the callback signals through the raw pointer instead of borrowing the `RefCell` again.
+    {
+        let mainloop_for_cb = Rc::clone(&mainloop);
+        context.borrow_mut().set_state_callback(Some(Box::new(move || {
+            unsafe { (*mainloop_for_cb.as_ptr()).signal(false) };
+        })));
+    }
+
+    if context
+        .borrow_mut()
+        .connect(None, ContextFlagSet::NOFLAGS, None)
+        .is_err()
+    {
+        return;
+    }
+
+    mainloop.borrow_mut().lock();
+
+    if mainloop.borrow_mut().start().is_err() {
+        mainloop.borrow_mut().unlock();
+        return;
+    }
+
+    loop {
+        match context.borrow().get_state() {
+            ContextState::Ready => break,
+            ContextState::Failed | ContextState::Terminated => {
+                mainloop.borrow_mut().unlock();
+                return;
+            }
+            _ => mainloop.borrow_mut().wait(),
+        }
+    }
+
+    // the state callback has done its job getting us to Ready; subsequent progress is driven
+    // by the subscribe callback instead
+    context.borrow_mut().set_state_callback(None);
+
+    {
+        let context_for_cb = Rc::clone(&context);
+        let tx_for_cb = tx.clone();
+        let dirty_for_cb = Arc::clone(&dirty);
+
+        context
+            .borrow_mut()
+            .set_subscribe_callback(Some(Box::new(move |facility, _operation, _index| {
+                if matches!(facility, Some(Facility::Sink) | Some(Facility::Server)) {
+                    read_default_sink(&context_for_cb, &tx_for_cb, &dirty_for_cb);
+                }
+            })));
+
+        context
+            .borrow_mut()
+            .subscribe(InterestMaskSet::SINK | InterestMaskSet::SERVER, |_| {});
+    }
+
+    // prime the block with an initial reading instead of waiting for the first event
+    read_default_sink(&context, &tx, &dirty);
+    ready.store(true, Ordering::Relaxed);
+
+    mainloop.borrow_mut().unlock();
+
+    // the threaded mainloop drives itself on its own internal thread from here, invoking our
+    // callbacks while holding its lock; just keep this thread parked so `context`/`mainloop`
+    // (and the lock we released above) aren't dropped out from under them
+    loop {
+        thread::park();
+    }
+}
+
+fn read_default_sink(context: &Rc<RefCell<Context>>, tx: &Sender<PulseReading>, dirty: &Arc<AtomicBool>) {
+    let context_for_sink = Rc::clone(context);
+    let tx = tx.clone();
+    let dirty = Arc::clone(dirty);
+
+    context
+        .borrow_mut()
+        .introspect()
+        .get_server_info(move |info| {
+            let sink_name = match &info.default_sink_name {
+                Some(name) => name.to_string(),
+                None => return,
+            };
+
+            let tx = tx.clone();
+            let dirty = dirty.clone();
+
+            context_for_sink
+                .borrow_mut()
+                .introspect()
+                .get_sink_info_by_name(&sink_name, move |result| {
+                    if let libpulse_binding::callbacks::ListResult::Item(sink) = result {
+                        let percent = (sink.volume.avg().0 as u64 * 100 / Volume::NORMAL.0 as u64)
+                            as i32;
+
+                        let _ = tx.send(PulseReading {
+                            percent,
+                            muted: sink.mute,
+                        });
+                        dirty.store(true, Ordering::Relaxed);
+                    }
+                });
+        });
+}